@@ -0,0 +1,128 @@
+// Persistent, on-disk cache of fetched pages so a previous session can be
+// revisited without hitting the network again.
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct Cache {
+    conn: Connection,
+}
+
+pub struct CachedPage {
+    pub fetched_at: i64,
+    pub status: i64,
+    pub content: String,
+}
+
+impl Cache {
+    pub fn open(path: &Path) -> Result<Cache> {
+        let conn = Connection::open(path)?;
+        Cache::init(conn)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Cache> {
+        Cache::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Cache> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS pages (
+                url TEXT PRIMARY KEY,
+                fetched_at INTEGER NOT NULL,
+                status INTEGER NOT NULL,
+                content TEXT NOT NULL
+            )",
+            [],
+        )?;
+        Ok(Cache { conn })
+    }
+
+    pub fn put(&self, url: &str, status: u16, content: &str) -> Result<()> {
+        let fetched_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        self.put_at(url, status, content, fetched_at)
+    }
+
+    /// Same as `put`, but with an explicit `fetched_at` instead of the
+    /// current time, so tests can control ordering deterministically.
+    fn put_at(&self, url: &str, status: u16, content: &str, fetched_at: i64) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO pages (url, fetched_at, status, content) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(url) DO UPDATE SET
+                fetched_at = excluded.fetched_at,
+                status = excluded.status,
+                content = excluded.content",
+            params![url, fetched_at, status as i64, content],
+        )?;
+        Ok(())
+    }
+
+    pub fn get(&self, url: &str) -> Result<Option<CachedPage>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT fetched_at, status, content FROM pages WHERE url = ?1")?;
+        let mut rows = stmt.query(params![url])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(CachedPage {
+                fetched_at: row.get(0)?,
+                status: row.get(1)?,
+                content: row.get(2)?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    pub fn list(&self) -> Result<Vec<(String, i64, i64)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT url, fetched_at, status FROM pages ORDER BY fetched_at DESC")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect::<rusqlite::Result<Vec<_>>>().map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn put_and_get_round_trip() {
+        let cache = Cache::open_in_memory().unwrap();
+        cache.put_at("http://x", 200, "<html/>", 1).unwrap();
+        let page = cache.get("http://x").unwrap().unwrap();
+        assert_eq!(page.fetched_at, 1);
+        assert_eq!(page.status, 200);
+        assert_eq!(page.content, "<html/>");
+    }
+
+    #[test]
+    fn get_returns_none_for_unknown_url() {
+        let cache = Cache::open_in_memory().unwrap();
+        assert!(cache.get("http://missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn put_upserts_on_conflicting_url() {
+        let cache = Cache::open_in_memory().unwrap();
+        cache.put_at("http://x", 200, "first", 1).unwrap();
+        cache.put_at("http://x", 304, "second", 2).unwrap();
+
+        let page = cache.get("http://x").unwrap().unwrap();
+        assert_eq!(page.fetched_at, 2);
+        assert_eq!(page.status, 304);
+        assert_eq!(page.content, "second");
+        // Still a single row for the url, not a second one appended.
+        assert_eq!(cache.list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn list_orders_most_recently_fetched_first() {
+        let cache = Cache::open_in_memory().unwrap();
+        cache.put_at("http://old", 200, "old", 1).unwrap();
+        cache.put_at("http://new", 200, "new", 2).unwrap();
+
+        let urls: Vec<String> = cache.list().unwrap().into_iter().map(|(url, _, _)| url).collect();
+        assert_eq!(urls, vec!["http://new", "http://old"]);
+    }
+}
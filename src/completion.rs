@@ -0,0 +1,158 @@
+// Context-aware tab completion for the REPL: command keywords at the start
+// of a line, tag names once inside `find tag`/`select`, and attribute names
+// once inside `attrs`/`values`.
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+use soup::prelude::*;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+const COMMANDS: &[&str] = &[
+    "cols", "find", "select", "url", "head", "cache", "history", "theme", "highlight", "lua",
+    "script", "sexp",
+];
+
+/// Completes against the document currently loaded into `contents`. Shared
+/// with `MarkupExplorer` via the same `Rc<RefCell<..>>` so completions
+/// always reflect the latest parsed page.
+pub struct MarkupCompleter {
+    contents: Rc<RefCell<Option<String>>>,
+}
+
+impl MarkupCompleter {
+    pub fn new(contents: Rc<RefCell<Option<String>>>) -> MarkupCompleter {
+        MarkupCompleter { contents }
+    }
+
+    fn tag_names(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        if let Some(contents) = self.contents.borrow().as_ref() {
+            let soup = soup::Soup::new(contents.as_str());
+            for node in soup.tag(true).find_all() {
+                names.insert(node.name().to_string());
+            }
+        }
+        names
+    }
+
+    fn attr_names(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        if let Some(contents) = self.contents.borrow().as_ref() {
+            let soup = soup::Soup::new(contents.as_str());
+            for node in soup.tag(true).find_all() {
+                for (name, _value) in node.attrs() {
+                    names.insert(name.to_string());
+                }
+            }
+        }
+        names
+    }
+}
+
+impl Completer for MarkupCompleter {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> Result<(usize, Vec<Pair>), ReadlineError> {
+        let words: Vec<&str> = line[..pos].split(' ').collect();
+        let current = *words.last().unwrap_or(&"");
+        let start = pos - current.len();
+
+        let names: Vec<String> = if words.len() <= 1 {
+            COMMANDS
+                .iter()
+                .filter(|c| c.starts_with(current))
+                .map(|c| c.to_string())
+                .collect()
+        } else {
+            let command = words[0];
+            let prev = words[words.len() - 2];
+            if prev == "tag" || (command == "select" && words.len() == 2) {
+                self.tag_names()
+                    .into_iter()
+                    .filter(|name| name.starts_with(current))
+                    .collect()
+            } else if prev == "attrs" || prev == "values" {
+                self.attr_names()
+                    .into_iter()
+                    .filter(|name| name.starts_with(current))
+                    .collect()
+            } else {
+                Vec::new()
+            }
+        };
+
+        Ok((
+            start,
+            names
+                .into_iter()
+                .map(|name| Pair {
+                    display: name.clone(),
+                    replacement: name,
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl Hinter for MarkupCompleter {
+    type Hint = String;
+}
+
+impl Highlighter for MarkupCompleter {}
+
+impl Validator for MarkupCompleter {}
+
+impl Helper for MarkupCompleter {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completer(contents: &str) -> MarkupCompleter {
+        MarkupCompleter::new(Rc::new(RefCell::new(Some(contents.to_string()))))
+    }
+
+    #[test]
+    fn tag_names_collects_distinct_tags() {
+        // `soup` runs full HTML5 parsing, which wraps a bare fragment in an
+        // implied `<html><head></head><body>...</body></html>`, so those
+        // wrapper tags are legitimately part of the document too.
+        let names = completer("<div><p>a</p><p>b</p></div>").tag_names();
+        assert_eq!(
+            names,
+            BTreeSet::from([
+                "html".to_string(),
+                "head".to_string(),
+                "body".to_string(),
+                "div".to_string(),
+                "p".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn attr_names_collects_distinct_attrs_across_tags() {
+        let names = completer(r#"<a href="/x"><p id="y">a</p></a>"#).attr_names();
+        assert_eq!(
+            names,
+            BTreeSet::from(["href".to_string(), "id".to_string()])
+        );
+    }
+
+    #[test]
+    fn names_are_empty_without_loaded_contents() {
+        let completer = MarkupCompleter::new(Rc::new(RefCell::new(None)));
+        assert!(completer.tag_names().is_empty());
+        assert!(completer.attr_names().is_empty());
+    }
+}
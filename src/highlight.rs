@@ -0,0 +1,176 @@
+// ANSI truecolor syntax highlighting for markup output, used by `head`.
+use anyhow::{anyhow, Result};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::as_24_bit_terminal_escaped;
+
+pub struct Highlighter {
+    syntax_set: SyntaxSet,
+    theme_set: ThemeSet,
+    theme: String,
+    enabled: bool,
+}
+
+impl Highlighter {
+    pub fn new() -> Highlighter {
+        Highlighter {
+            syntax_set: SyntaxSet::load_defaults_newlines(),
+            theme_set: ThemeSet::load_defaults(),
+            theme: "base16-ocean.dark".to_string(),
+            enabled: true,
+        }
+    }
+
+    pub fn set_theme(&mut self, name: &str) -> Result<()> {
+        if !self.theme_set.themes.contains_key(name) {
+            return Err(anyhow!("Unknown theme: {}", name));
+        }
+        self.theme = name.to_string();
+        Ok(())
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    fn syntax(&self) -> &SyntaxReference {
+        self.syntax_set
+            .find_syntax_by_extension("html")
+            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
+    }
+
+    /// Render the first `max` lines of `content`, highlighting each (unless
+    /// disabled) and truncating it to `cols` visible characters the same
+    /// way the un-highlighted `head` path does.
+    pub fn render_lines(&self, content: &str, cols: Option<usize>, max: u32) -> Vec<String> {
+        if !self.enabled {
+            return content
+                .split('\n')
+                .take(max as usize)
+                .map(|line| truncate_plain(line, cols))
+                .collect();
+        }
+        let theme = &self.theme_set.themes[&self.theme];
+        let mut highlighter = HighlightLines::new(self.syntax(), theme);
+        content
+            .split('\n')
+            .take(max as usize)
+            .map(|line| {
+                // `load_defaults_newlines()` expects each line handed to
+                // `highlight_line` to carry its trailing "\n" — EOL-anchored
+                // grammar rules (e.g. inside embedded <script>/<style>
+                // blocks) desync without it. Add it back, then drop the
+                // single '\n' char back out of the rendered/escaped result.
+                let with_newline = format!("{}\n", line);
+                let ranges = highlighter
+                    .highlight_line(&with_newline, &self.syntax_set)
+                    .unwrap_or_default();
+                let mut rendered = as_24_bit_terminal_escaped(&ranges[..], false);
+                if let Some(pos) = rendered.rfind('\n') {
+                    rendered.remove(pos);
+                }
+                truncate_highlighted(&rendered, cols)
+            })
+            .collect()
+    }
+}
+
+fn truncate_plain(line: &str, cols: Option<usize>) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    match cols {
+        Some(cols) if chars.len() > cols => {
+            let trunc: String = chars.iter().take(cols - 3).collect();
+            format!("{}...", trunc)
+        }
+        _ => line.to_string(),
+    }
+}
+
+/// The number of non-escape-sequence characters in `s`.
+fn visible_len(s: &str) -> usize {
+    let mut len = 0;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            while let Some(&n) = chars.peek() {
+                chars.next();
+                if n == 'm' {
+                    break;
+                }
+            }
+            continue;
+        }
+        len += 1;
+    }
+    len
+}
+
+/// Truncate an ANSI-escaped line to `cols` visible characters, leaving the
+/// escape sequences themselves untouched so the ellipsis lands at the right
+/// column rather than being thrown off by invisible color codes.
+fn truncate_highlighted(rendered: &str, cols: Option<usize>) -> String {
+    let cols = match cols {
+        Some(cols) => cols,
+        None => return rendered.to_string(),
+    };
+    if visible_len(rendered) <= cols {
+        return rendered.to_string();
+    }
+    let mut out = String::new();
+    let mut visible = 0;
+    let mut chars = rendered.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            out.push(c);
+            while let Some(&n) = chars.peek() {
+                out.push(n);
+                chars.next();
+                if n == 'm' {
+                    break;
+                }
+            }
+            continue;
+        }
+        if visible >= cols.saturating_sub(3) {
+            break;
+        }
+        out.push(c);
+        visible += 1;
+    }
+    out.push_str("...\x1b[0m");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visible_len_ignores_escape_codes() {
+        let line = "\x1b[38;2;255;0;0mred\x1b[0m text";
+        assert_eq!(visible_len(line), "red text".len());
+    }
+
+    #[test]
+    fn truncate_highlighted_leaves_short_lines_alone() {
+        let line = "\x1b[1mshort\x1b[0m";
+        assert_eq!(truncate_highlighted(line, Some(80)), line);
+    }
+
+    #[test]
+    fn truncate_highlighted_cuts_on_visible_columns_only() {
+        let line = "\x1b[1mabcdefghij\x1b[0m";
+        let truncated = truncate_highlighted(line, Some(5));
+        // 2 real characters (5 - 3 for "...") plus the ellipsis itself.
+        assert_eq!(visible_len(&truncated), 5);
+        assert!(truncated.starts_with("\x1b[1m"));
+        assert!(truncated.ends_with("...\x1b[0m"));
+    }
+
+    #[test]
+    fn truncate_highlighted_is_noop_without_cols() {
+        let line = "\x1b[1mabcdefghij\x1b[0m";
+        assert_eq!(truncate_highlighted(line, None), line);
+    }
+}
@@ -0,0 +1,167 @@
+// Structured command history: every line entered in the REPL is recorded
+// with its timestamp, the page that was loaded at the time, and whether it
+// succeeded, so a prior exploration session can be searched and replayed.
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct History {
+    conn: Connection,
+}
+
+pub struct HistoryEntry {
+    pub id: i64,
+    pub line: String,
+    pub timestamp: i64,
+    pub url: Option<String>,
+    pub ok: bool,
+}
+
+impl History {
+    pub fn open(path: &Path) -> Result<History> {
+        let conn = Connection::open(path)?;
+        History::init(conn)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<History> {
+        History::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<History> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS entries (
+                id INTEGER PRIMARY KEY,
+                line TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                url TEXT,
+                ok INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        Ok(History { conn })
+    }
+
+    pub fn record(&self, line: &str, url: Option<&str>, ok: bool) -> Result<()> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+        self.conn.execute(
+            "INSERT INTO entries (line, timestamp, url, ok) VALUES (?1, ?2, ?3, ?4)",
+            params![line, timestamp, url, ok as i64],
+        )?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<String>> {
+        let mut stmt = self.conn.prepare("SELECT line FROM entries WHERE id = ?1")?;
+        let mut rows = stmt.query(params![id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Fuzzy-search prior commands for `query`, ranking the results so that
+    /// the tightest matching span (the command whose matched characters are
+    /// most tightly clustered) floats to the top.
+    pub fn search(&self, query: &str) -> Result<Vec<HistoryEntry>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, line, timestamp, url, ok FROM entries ORDER BY id")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(HistoryEntry {
+                id: row.get(0)?,
+                line: row.get(1)?,
+                timestamp: row.get(2)?,
+                url: row.get(3)?,
+                ok: row.get::<_, i64>(4)? != 0,
+            })
+        })?;
+        let mut matches: Vec<(usize, HistoryEntry)> = Vec::new();
+        for entry in rows {
+            let entry = entry?;
+            if let Some(span) = shortest_span(&entry.line, query) {
+                matches.push((span, entry));
+            }
+        }
+        matches.sort_by_key(|(span, _)| *span);
+        Ok(matches.into_iter().map(|(_, entry)| entry).collect())
+    }
+}
+
+/// The length, in characters, of the shortest substring of `haystack` that
+/// contains every character of `needle` in order (a subsequence match),
+/// case-insensitively. Returns `None` if `needle` is not a subsequence.
+fn shortest_span(haystack: &str, needle: &str) -> Option<usize> {
+    let hay: Vec<char> = haystack.chars().collect();
+    let needle: Vec<char> = needle.chars().collect();
+    if needle.is_empty() {
+        return Some(0);
+    }
+    let mut best: Option<usize> = None;
+    for start in 0..hay.len() {
+        if !hay[start].eq_ignore_ascii_case(&needle[0]) {
+            continue;
+        }
+        let mut ni = 1;
+        let mut end = start;
+        for (i, c) in hay.iter().enumerate().skip(start + 1) {
+            if ni >= needle.len() {
+                break;
+            }
+            if c.eq_ignore_ascii_case(&needle[ni]) {
+                ni += 1;
+                end = i;
+            }
+        }
+        if ni == needle.len() {
+            let span = end - start + 1;
+            if best.map_or(true, |b| span < b) {
+                best = Some(span);
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortest_span_prefers_tight_clusters() {
+        // A contiguous match spans exactly the needle's length...
+        assert_eq!(shortest_span("select div.content", "div"), Some(3));
+        // ...while a scattered one spans everything in between.
+        assert_eq!(shortest_span("d.i.v", "div"), Some(5));
+    }
+
+    #[test]
+    fn shortest_span_is_case_insensitive() {
+        assert_eq!(shortest_span("SELECT div", "select"), Some(6));
+    }
+
+    #[test]
+    fn shortest_span_none_when_not_a_subsequence() {
+        assert_eq!(shortest_span("select div", "xyz"), None);
+    }
+
+    #[test]
+    fn shortest_span_empty_needle_matches_everything() {
+        assert_eq!(shortest_span("anything", ""), Some(0));
+    }
+
+    #[test]
+    fn search_ranks_tightest_match_first() {
+        let history = History::open_in_memory().unwrap();
+        // "div" is scattered across this line (d...i...v far apart).
+        history.record("debug inspect everything", None, true).unwrap();
+        // "div" appears contiguously here, so it should rank first.
+        history.record("select div.content", Some("http://x"), true).unwrap();
+        history.record("cache list", None, false).unwrap();
+
+        let results = history.search("div").unwrap();
+        let lines: Vec<&str> = results.iter().map(|e| e.line.as_str()).collect();
+        assert_eq!(lines, vec!("select div.content", "debug inspect everything"));
+    }
+}
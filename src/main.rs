@@ -1,24 +1,74 @@
 use anyhow::{anyhow, Result};
-use clap;
+use clap::Parser;
 use reqwest;
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
+use html5ever::rcdom::Handle;
 use soup::prelude::*;
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
 use tokio;
 
+mod cache;
+mod completion;
+mod highlight;
+mod history;
+mod script;
+mod selector;
+mod sexp;
+
+/// Explore the markup of a page from the command line.
+#[derive(Parser)]
+struct Args {
+    /// Read exclusively from the page cache; never hit the network.
+    #[clap(long)]
+    offline: bool,
+}
+
 struct MarkupExplorer {
     url: Option<String>,
-    contents: Option<String>,
+    // Shared with the REPL's tab completer so it always sees the page that
+    // is currently loaded.
+    contents: Rc<RefCell<Option<String>>>,
     cols: Option<usize>,
+    matches: Vec<Handle>,
+    cache: cache::Cache,
+    offline: bool,
+    history: history::History,
+    highlighter: highlight::Highlighter,
+    scripting: script::Scripting,
 }
 
 impl MarkupExplorer {
-    fn new() -> MarkupExplorer {
-        MarkupExplorer {
+    fn new(
+        cache: cache::Cache,
+        offline: bool,
+        history: history::History,
+        contents: Rc<RefCell<Option<String>>>,
+    ) -> Result<MarkupExplorer> {
+        let scripting = script::Scripting::new(contents.clone())?;
+        Ok(MarkupExplorer {
             url: None,
-            contents: None,
+            contents,
             cols: Some(80),
-        }
+            matches: Vec::new(),
+            cache,
+            offline,
+            history,
+            highlighter: highlight::Highlighter::new(),
+            scripting,
+        })
+    }
+
+    /// Parse the currently loaded page for the command handlers. The tab
+    /// completer and the Lua `doc.select` read the same shared `contents`
+    /// but parse it themselves.
+    fn document(&self) -> Result<soup::Soup> {
+        let contents = self.contents.borrow();
+        let contents = contents.as_ref().ok_or_else(|| anyhow!("No contents to parse."))?;
+        Ok(soup::Soup::new(contents.as_str()))
     }
 
     fn parse_line(&self, line: String) -> Vec<String> {
@@ -55,20 +105,41 @@ impl MarkupExplorer {
 
     async fn url(&mut self, url: &str) -> Result<()> {
         self.url = Some(url.to_string());
+        self.matches = Vec::new();
+        if self.offline {
+            let page = self
+                .cache
+                .get(url)?
+                .ok_or_else(|| anyhow!("No cached page for {}", url))?;
+            *self.contents.borrow_mut() = Some(page.content);
+            return Ok(());
+        }
         let response: reqwest::Response = reqwest::get(url).await?;
         if response.status().is_server_error() {
             return Err(anyhow!("server error: {}", response.status()));
         }
-        self.contents = Some(response.text().await?);
+        let status = response.status().as_u16();
+        let text = response.text().await?;
+        self.cache.put(url, status, &text)?;
+        *self.contents.borrow_mut() = Some(text);
         Ok(())
     }
 
-    async fn process_line(&mut self, line: String) -> Result<()> {
+    // Boxed so `history replay` can recurse into `process_line` without
+    // requiring an infinitely-sized future.
+    fn process_line<'a>(&'a mut self, line: String) -> Pin<Box<dyn Future<Output = Result<()>> + 'a>> {
+        Box::pin(self.process_line_inner(line))
+    }
+
+    async fn process_line_inner(&mut self, line: String) -> Result<()> {
         let mut args = self.parse_line(line);
         if args.len() == 0 {
             return Ok(());
         }
         let command = args.remove(0);
+        if self.scripting.has_command(&command)? {
+            return self.scripting.call_command(&command, args);
+        }
         match command.as_str() {
             "cols" => {
                 let count = args.remove(0);
@@ -79,7 +150,7 @@ impl MarkupExplorer {
                 }
             }
             "find" => {
-                let soup = soup::Soup::new(self.contents.as_ref().expect("No contents to parse."));
+                let soup = self.document()?;
                 let mut iter = args.iter().peekable();
                 let mut arg = iter.next();
                 let mut node = None;
@@ -125,6 +196,129 @@ impl MarkupExplorer {
                     }
                     arg = iter.next();
                 }
+                if let Some(node) = node {
+                    self.matches = vec![node];
+                }
+            }
+            "select" => {
+                let css = args.remove(0);
+                let soup = self.document()?;
+                let matches = selector::select(&soup, &css)?;
+                for node in &matches {
+                    let attrs: Vec<String> = node
+                        .attrs()
+                        .into_iter()
+                        .map(|(name, value)| format!("{}=\"{}\"", name, value))
+                        .collect();
+                    if attrs.is_empty() {
+                        println!("{}", node.name());
+                    } else {
+                        println!("{} {}", node.name(), attrs.join(" "));
+                    }
+                }
+                let mut iter = args.iter().peekable();
+                let mut arg = iter.next();
+                while arg.is_some() {
+                    let value = arg.unwrap();
+                    match value.as_str() {
+                        "attrs" => {
+                            for node in &matches {
+                                for (name, _value) in node.attrs() {
+                                    println!("{}", name);
+                                }
+                            }
+                        }
+                        "values" => {
+                            for node in &matches {
+                                for (name, value) in node.attrs() {
+                                    println!("{} = {}", name, value);
+                                }
+                            }
+                        }
+                        "tree" => {
+                            for node in &matches {
+                                for child in node.children() {
+                                    println!("{}", child.name());
+                                }
+                            }
+                        }
+                        v => {
+                            return Err(anyhow!("Unrecognized param: {}", v));
+                        }
+                    }
+                    arg = iter.next();
+                }
+                self.matches = matches;
+            }
+            "sexp" => {
+                if self.matches.is_empty() {
+                    let soup = self.document()?;
+                    for node in soup.children() {
+                        print!("{}", sexp::write(&node));
+                    }
+                } else {
+                    for node in &self.matches {
+                        print!("{}", sexp::write(node));
+                    }
+                }
+            }
+            "lua" => {
+                let expr = args.join(" ");
+                self.scripting.eval(&expr)?;
+            }
+            "script" => {
+                let path = args.remove(0);
+                self.scripting.run_file(std::path::Path::new(&path))?;
+            }
+            "history" => {
+                let sub = args.remove(0);
+                match sub.as_str() {
+                    "search" => {
+                        let query = args.remove(0);
+                        for entry in self.history.search(&query)? {
+                            println!(
+                                "{}\t{}\t{}",
+                                entry.id,
+                                entry.url.as_deref().unwrap_or("-"),
+                                entry.line
+                            );
+                        }
+                    }
+                    "replay" => {
+                        let id: i64 = args.remove(0).parse()?;
+                        let line = self
+                            .history
+                            .get(id)?
+                            .ok_or_else(|| anyhow!("No history entry {}", id))?;
+                        self.process_line(line).await?;
+                    }
+                    v => {
+                        return Err(anyhow!("Unrecognized history sub-command: {}", v));
+                    }
+                }
+            }
+            "cache" => {
+                let sub = args.remove(0);
+                match sub.as_str() {
+                    "list" => {
+                        for (url, fetched_at, status) in self.cache.list()? {
+                            println!("{}\t{}\t{}", fetched_at, status, url);
+                        }
+                    }
+                    "open" => {
+                        let url = args.remove(0);
+                        let page = self
+                            .cache
+                            .get(&url)?
+                            .ok_or_else(|| anyhow!("No cached page for {}", url))?;
+                        *self.contents.borrow_mut() = Some(page.content);
+                        self.url = Some(url);
+                        self.matches = Vec::new();
+                    }
+                    v => {
+                        return Err(anyhow!("Unrecognized cache sub-command: {}", v));
+                    }
+                }
             }
             "url" => {
                 let url = &args[0];
@@ -133,27 +327,27 @@ impl MarkupExplorer {
             "head" => {
                 let max = &args[0];
                 let max: u32 = max.parse()?;
-                let mut count = 0;
-                match &self.contents {
+                match self.contents.borrow().as_ref() {
                     None => return Err(anyhow!("No contents available.")),
                     Some(c) => {
-                        for line in c.split("\n") {
-                            let chars: Vec<char> = line.chars().collect();
-                            if self.cols.is_some() && chars.len() > self.cols.unwrap() {
-                                let trunc: String =
-                                    chars.iter().take(self.cols.unwrap() - 3).collect();
-                                println!("{}...", trunc);
-                            } else {
-                                println!("{}", line);
-                            }
-                            count += 1;
-                            if count >= max {
-                                break;
-                            }
+                        for line in self.highlighter.render_lines(c, self.cols, max) {
+                            println!("{}", line);
                         }
                     }
                 }
             }
+            "theme" => {
+                let name = args.remove(0);
+                self.highlighter.set_theme(&name)?;
+            }
+            "highlight" => {
+                let state = args.remove(0);
+                match state.as_str() {
+                    "on" => self.highlighter.set_enabled(true),
+                    "off" => self.highlighter.set_enabled(false),
+                    v => return Err(anyhow!("Unrecognized param: {}", v)),
+                }
+            }
             _ => {}
         };
         Ok(())
@@ -162,6 +356,7 @@ impl MarkupExplorer {
 
 #[tokio::main]
 async fn main() {
+    let args = Args::parse();
     let home = dirs::home_dir().expect("Unable to find home dir.");
     let history_dir = home.join(".me");
     if !history_dir.exists() {
@@ -170,19 +365,29 @@ async fn main() {
             std::process::exit(1);
         }
     }
-    let history = history_dir.join("history");
-    // `()` can be used when no completer is required
-    let mut rl = Editor::<()>::new();
-    if rl.load_history(&history).is_err() {
-        println!("No previous history.");
+    let contents = Rc::new(RefCell::new(None));
+    let mut rl = Editor::<completion::MarkupCompleter>::new();
+    rl.set_helper(Some(completion::MarkupCompleter::new(contents.clone())));
+    let cache = cache::Cache::open(&history_dir.join("cache.db")).expect("Unable to open cache.");
+    let history =
+        history::History::open(&history_dir.join("history.db")).expect("Unable to open history.");
+    let mut me = MarkupExplorer::new(cache, args.offline, history, contents)
+        .expect("Unable to initialize Lua scripting.");
+    if let Err(err) = me.scripting.run_init(&history_dir.join("init.lua")) {
+        println!("Error running init.lua: {}", err);
     }
-    let mut me = MarkupExplorer::new();
     loop {
         let readline = rl.readline(">> ");
         match readline {
             Ok(line) => {
                 rl.add_history_entry(line.as_str());
-                match me.process_line(line).await {
+                let url_before = me.url.clone();
+                let result = me.process_line(line.clone()).await;
+                if let Err(err) = me.history.record(&line, url_before.as_deref(), result.is_ok())
+                {
+                    println!("Unable to record history entry: {}", err);
+                }
+                match result {
                     Ok(_) => (),
                     Err(err) => println!("Error: {}", err),
                 };
@@ -201,7 +406,6 @@ async fn main() {
             }
         }
     }
-    rl.save_history(&history).unwrap();
 }
 
 #[cfg(test)]
@@ -210,7 +414,13 @@ mod tests {
 
     #[test]
     fn parse_line() {
-        let me = MarkupExplorer::new();
+        let me = MarkupExplorer::new(
+            cache::Cache::open_in_memory().unwrap(),
+            false,
+            history::History::open_in_memory().unwrap(),
+            Rc::new(RefCell::new(None)),
+        )
+        .unwrap();
         // Space separated
         assert_eq!(
             me.parse_line("cat ~/file".to_string()),
@@ -0,0 +1,165 @@
+// Embedded Lua scripting so users can script exploration beyond the
+// built-in verbs: `lua <expr>`, `script <file>`, and a `~/.me/init.lua`
+// that runs on startup and can register new REPL commands.
+use anyhow::Result;
+use html5ever::rcdom::Handle;
+use mlua::{Lua, Table, UserData, UserDataMethods, Variadic};
+use soup::prelude::*;
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+struct LuaNode(Handle);
+
+impl UserData for LuaNode {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("name", |_, this, ()| Ok(this.0.name().to_string()));
+        methods.add_method("attr", |_, this, key: String| Ok(this.0.attrs().get(&key).cloned()));
+        methods.add_method("text", |_, this, ()| Ok(this.0.text()));
+        methods.add_method("children", |_, this, ()| {
+            Ok(this.0.children().map(LuaNode).collect::<Vec<_>>())
+        });
+    }
+}
+
+pub struct Scripting {
+    lua: Lua,
+}
+
+impl Scripting {
+    pub fn new(contents: Rc<RefCell<Option<String>>>) -> Result<Scripting> {
+        let lua = Lua::new();
+        let doc = lua.create_table()?;
+        let select = lua.create_function(move |_, css: String| {
+            let contents = contents.borrow();
+            let contents = contents
+                .as_ref()
+                .ok_or_else(|| mlua::Error::RuntimeError("No contents to parse.".to_string()))?;
+            let soup = soup::Soup::new(contents.as_str());
+            let nodes = crate::selector::select(&soup, &css)
+                .map_err(|err| mlua::Error::RuntimeError(err.to_string()))?;
+            Ok(nodes.into_iter().map(LuaNode).collect::<Vec<_>>())
+        })?;
+        doc.set("select", select)?;
+        lua.globals().set("doc", doc)?;
+        lua.globals().set("commands", lua.create_table()?)?;
+        Ok(Scripting { lua })
+    }
+
+    pub fn run_init(&self, path: &Path) -> Result<()> {
+        if path.exists() {
+            self.run_file(path)?;
+        }
+        Ok(())
+    }
+
+    pub fn eval(&self, expr: &str) -> Result<()> {
+        self.lua.load(expr).exec()?;
+        Ok(())
+    }
+
+    pub fn run_file(&self, path: &Path) -> Result<()> {
+        let source = std::fs::read_to_string(path)?;
+        self.lua.load(&source).exec()?;
+        Ok(())
+    }
+
+    /// True if a `lua`/`script` invocation has registered a REPL command
+    /// under this name in the `commands` table.
+    pub fn has_command(&self, name: &str) -> Result<bool> {
+        let commands: Table = self.lua.globals().get("commands")?;
+        Ok(commands.contains_key(name)?)
+    }
+
+    pub fn call_command(&self, name: &str, args: Vec<String>) -> Result<()> {
+        let commands: Table = self.lua.globals().get("commands")?;
+        let f: mlua::Function = commands.get(name)?;
+        // Spread the args as separate Lua arguments (`Variadic`), rather
+        // than one `Vec<String>` wrapped into a single table argument.
+        f.call::<_, ()>(Variadic::from_iter(args))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scripting(contents: &str) -> Scripting {
+        Scripting::new(Rc::new(RefCell::new(Some(contents.to_string())))).unwrap()
+    }
+
+    #[test]
+    fn doc_select_exposes_matching_nodes_to_lua() {
+        let scripting = scripting(r#"<div><a href="/x">hi</a></div>"#);
+        scripting
+            .eval(
+                r#"
+                local links = doc.select("a")
+                count = #links
+                name = links[1]:name()
+                "#,
+            )
+            .unwrap();
+        assert_eq!(scripting.lua.globals().get::<_, i64>("count").unwrap(), 1);
+        assert_eq!(scripting.lua.globals().get::<_, String>("name").unwrap(), "a");
+    }
+
+    #[test]
+    fn lua_node_name_attr_and_text_are_reachable_from_lua() {
+        let scripting = scripting(r#"<a href="/x">hi</a>"#);
+        scripting
+            .eval(
+                r#"
+                local a = doc.select("a")[1]
+                name = a:name()
+                href = a:attr("href")
+                missing = a:attr("title")
+                text = a:text()
+                "#,
+            )
+            .unwrap();
+        assert_eq!(scripting.lua.globals().get::<_, String>("name").unwrap(), "a");
+        assert_eq!(
+            scripting.lua.globals().get::<_, String>("href").unwrap(),
+            "/x"
+        );
+        assert!(scripting
+            .lua
+            .globals()
+            .get::<_, Option<String>>("missing")
+            .unwrap()
+            .is_none());
+        assert_eq!(scripting.lua.globals().get::<_, String>("text").unwrap(), "hi");
+    }
+
+    #[test]
+    fn registered_commands_are_visible_via_has_command() {
+        let scripting = scripting("<div></div>");
+        assert!(!scripting.has_command("links").unwrap());
+        scripting
+            .eval(r#"commands["links"] = function() end"#)
+            .unwrap();
+        assert!(scripting.has_command("links").unwrap());
+    }
+
+    #[test]
+    fn call_command_spreads_args_positionally() {
+        let scripting = scripting("<div></div>");
+        scripting
+            .eval(
+                r#"
+                commands["echo"] = function(a, b)
+                    first = a
+                    second = b
+                end
+                "#,
+            )
+            .unwrap();
+        scripting
+            .call_command("echo", vec!["one".to_string(), "two".to_string()])
+            .unwrap();
+        assert_eq!(scripting.lua.globals().get::<_, String>("first").unwrap(), "one");
+        assert_eq!(scripting.lua.globals().get::<_, String>("second").unwrap(), "two");
+    }
+}
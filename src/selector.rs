@@ -0,0 +1,290 @@
+// A small CSS selector engine: enough to resolve selectors like
+// `div.content > a[href]` against a `soup::Soup` document without pulling in
+// a full CSS engine as a dependency.
+use anyhow::{anyhow, Result};
+use html5ever::rcdom::Handle;
+use soup::prelude::*;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum AttrPredicate {
+    Exists(String),
+    Equals(String, String),
+    StartsWith(String, String),
+    EndsWith(String, String),
+    Contains(String, String),
+}
+
+impl AttrPredicate {
+    fn matches(&self, attrs: &HashMap<String, String>) -> bool {
+        match self {
+            AttrPredicate::Exists(name) => attrs.contains_key(name),
+            AttrPredicate::Equals(name, value) => attrs.get(name) == Some(value),
+            AttrPredicate::StartsWith(name, value) => {
+                attrs.get(name).map_or(false, |v| v.starts_with(value.as_str()))
+            }
+            AttrPredicate::EndsWith(name, value) => {
+                attrs.get(name).map_or(false, |v| v.ends_with(value.as_str()))
+            }
+            AttrPredicate::Contains(name, value) => {
+                attrs.get(name).map_or(false, |v| v.contains(value.as_str()))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct CompoundSelector {
+    tag: Option<String>,
+    classes: HashSet<String>,
+    id: Option<String>,
+    attrs: Vec<AttrPredicate>,
+}
+
+impl CompoundSelector {
+    fn matches(&self, node: &Handle) -> bool {
+        if let Some(tag) = &self.tag {
+            if node.name() != tag.as_str() {
+                return false;
+            }
+        }
+        let attrs: HashMap<String, String> = node.attrs().into_iter().collect();
+        if !self.classes.is_empty() {
+            let classes: HashSet<String> = attrs
+                .get("class")
+                .map(|c| c.split_whitespace().map(|s| s.to_string()).collect())
+                .unwrap_or_default();
+            if !self.classes.is_subset(&classes) {
+                return false;
+            }
+        }
+        if let Some(id) = &self.id {
+            if attrs.get("id") != Some(id) {
+                return false;
+            }
+        }
+        self.attrs.iter().all(|p| p.matches(&attrs))
+    }
+}
+
+/// A parsed selector: a chain of compound selectors joined by combinators,
+/// read left to right the way the user wrote it (`self.parts[0]` is the
+/// leftmost/outermost compound).
+struct Selector {
+    parts: Vec<CompoundSelector>,
+    // combinators[i] joins parts[i] to parts[i + 1].
+    combinators: Vec<Combinator>,
+}
+
+impl Selector {
+    fn parse(input: &str) -> Result<Selector> {
+        let normalized = input.replace('>', " > ");
+        let words: Vec<&str> = normalized.split_whitespace().collect();
+        if words.is_empty() {
+            return Err(anyhow!("empty selector"));
+        }
+        let mut parts = Vec::new();
+        let mut combinators = Vec::new();
+        let mut pending = None;
+        for word in words {
+            if word == ">" {
+                pending = Some(Combinator::Child);
+                continue;
+            }
+            if !parts.is_empty() {
+                combinators.push(pending.take().unwrap_or(Combinator::Descendant));
+            }
+            parts.push(parse_compound(word)?);
+        }
+        Ok(Selector { parts, combinators })
+    }
+
+    // Matching proceeds right-to-left: the caller has already checked that
+    // `node` satisfies the rightmost compound selector, so this only has to
+    // confirm the ancestor chain satisfies the rest. A descendant step falls
+    // back to farther ancestors when the nearest match fails the rest.
+    fn matches_ancestors(&self, node: &Handle) -> bool {
+        // True if parts[..idx] are satisfied above `node`, which matched parts[idx].
+        fn rest(selector: &Selector, node: &Handle, idx: usize) -> bool {
+            if idx == 0 {
+                return true;
+            }
+            let part = &selector.parts[idx - 1];
+            match selector.combinators[idx - 1] {
+                Combinator::Child => match node.parent() {
+                    Some(parent) => part.matches(&parent) && rest(selector, &parent, idx - 1),
+                    None => false,
+                },
+                Combinator::Descendant => {
+                    let mut current = node.parent();
+                    while let Some(ancestor) = current {
+                        if part.matches(&ancestor) && rest(selector, &ancestor, idx - 1) {
+                            return true;
+                        }
+                        current = ancestor.parent();
+                    }
+                    false
+                }
+            }
+        }
+        rest(self, node, self.parts.len() - 1)
+    }
+}
+
+fn take_ident(chars: &mut Peekable<Chars>) -> String {
+    let mut ident = String::new();
+    while let Some(&c) = chars.peek() {
+        if c == '.' || c == '#' || c == '[' {
+            break;
+        }
+        ident.push(c);
+        chars.next();
+    }
+    ident
+}
+
+fn parse_attr(body: &str) -> Result<AttrPredicate> {
+    let ops: [(&str, fn(String, String) -> AttrPredicate); 4] = [
+        ("^=", AttrPredicate::StartsWith),
+        ("$=", AttrPredicate::EndsWith),
+        ("*=", AttrPredicate::Contains),
+        ("=", AttrPredicate::Equals),
+    ];
+    for (op, ctor) in ops {
+        if let Some(idx) = body.find(op) {
+            let name = body[..idx].to_string();
+            let value = body[idx + op.len()..].trim_matches('"').to_string();
+            return Ok(ctor(name, value));
+        }
+    }
+    if body.is_empty() {
+        return Err(anyhow!("empty attribute selector"));
+    }
+    Ok(AttrPredicate::Exists(body.to_string()))
+}
+
+fn parse_compound(word: &str) -> Result<CompoundSelector> {
+    let mut compound = CompoundSelector::default();
+    let mut chars = word.chars().peekable();
+    let mut tag = String::new();
+    while let Some(&c) = chars.peek() {
+        if c == '.' || c == '#' || c == '[' {
+            break;
+        }
+        tag.push(c);
+        chars.next();
+    }
+    if !tag.is_empty() && tag != "*" {
+        compound.tag = Some(tag);
+    }
+    while let Some(&c) = chars.peek() {
+        match c {
+            '.' => {
+                chars.next();
+                compound.classes.insert(take_ident(&mut chars));
+            }
+            '#' => {
+                chars.next();
+                compound.id = Some(take_ident(&mut chars));
+            }
+            '[' => {
+                chars.next();
+                let mut body = String::new();
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    body.push(c);
+                }
+                compound.attrs.push(parse_attr(&body)?);
+            }
+            _ => return Err(anyhow!("unexpected character '{}' in selector", c)),
+        }
+    }
+    Ok(compound)
+}
+
+/// Run `css` against every element in `soup`, returning every node that
+/// satisfies it.
+pub fn select(soup: &soup::Soup, css: &str) -> Result<Vec<Handle>> {
+    let selector = Selector::parse(css)?;
+    let last = selector.parts.last().expect("selector must have a part");
+    Ok(soup
+        .tag(true)
+        .find_all()
+        .filter(|node| last.matches(node) && selector.matches_ancestors(node))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTML: &str = r#"
+        <div class="content">
+            <p id="intro">hello</p>
+            <ul>
+                <li><a href="/a">a</a></li>
+                <li><a href="/b" class="external">b</a></li>
+            </ul>
+        </div>
+        <div class="sidebar"><a href="/c">c</a></div>
+    "#;
+
+    fn names(css: &str) -> Vec<String> {
+        let soup = soup::Soup::new(HTML);
+        select(&soup, css)
+            .unwrap()
+            .iter()
+            .map(|n| n.name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn tag_selector() {
+        assert_eq!(names("p"), vec!("p"));
+    }
+
+    #[test]
+    fn class_selector() {
+        assert_eq!(names(".sidebar").len(), 1);
+    }
+
+    #[test]
+    fn id_selector() {
+        assert_eq!(names("#intro"), vec!("p"));
+    }
+
+    #[test]
+    fn attr_predicates() {
+        assert_eq!(names("a[href^=\"/a\"]").len(), 1);
+        assert_eq!(names("a[href$=\"b\"]").len(), 1);
+        assert_eq!(names("a[class*=extern]").len(), 1);
+        assert_eq!(names("a[href]").len(), 3);
+    }
+
+    #[test]
+    fn descendant_combinator_matches_any_ancestor() {
+        // `div a` should reach the anchors nested inside the `ul`/`li` too,
+        // not just direct children.
+        assert_eq!(names("div a").len(), 3);
+    }
+
+    #[test]
+    fn child_combinator_requires_direct_parent() {
+        // `ul > a` has no direct anchor children of `ul` (they're under
+        // `li`), so this should match nothing...
+        assert_eq!(names("ul > a").len(), 0);
+        // ...while `li > a` should match both.
+        assert_eq!(names("li > a").len(), 2);
+    }
+}
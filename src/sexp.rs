@@ -0,0 +1,121 @@
+// Serialize a parsed document (or a single node) to an s-expression form of
+// markup: `(tagname (@ attr "value" ...) child ... "text")`. Far easier to
+// diff and grep than raw HTML, and pairs naturally with `select`.
+use html5ever::rcdom::{Handle, NodeData};
+use soup::prelude::*;
+
+pub fn write(node: &Handle) -> String {
+    let mut out = String::new();
+    write_node(node, 0, &mut out);
+    out
+}
+
+fn write_node(node: &Handle, depth: usize, out: &mut String) {
+    match &node.data {
+        NodeData::Text { contents } => {
+            // Whitespace-only text nodes matter too: the space between
+            // `<span>a</span> <span>b</span>` is itself a text node, and
+            // dropping it would lose significant markup on round-trip.
+            let text = contents.borrow();
+            out.push_str(&indent(depth));
+            out.push('"');
+            out.push_str(&escape(&text));
+            out.push_str("\"\n");
+        }
+        NodeData::Element { .. } => {
+            out.push_str(&indent(depth));
+            out.push('(');
+            out.push_str(node.name());
+            let attrs: Vec<(String, String)> = node.attrs().into_iter().collect();
+            if !attrs.is_empty() {
+                out.push_str(" (@");
+                for (name, value) in &attrs {
+                    out.push(' ');
+                    out.push_str(name);
+                    out.push_str(" \"");
+                    out.push_str(&escape(value));
+                    out.push('"');
+                }
+                out.push(')');
+            }
+            let children: Vec<Handle> = node.children().collect();
+            if children.is_empty() {
+                out.push_str(")\n");
+                return;
+            }
+            out.push('\n');
+            for child in &children {
+                write_node(child, depth + 1, out);
+            }
+            out.push_str(&indent(depth));
+            out.push_str(")\n");
+        }
+        // Document/doctype/comment/processing-instruction nodes carry no
+        // markup of their own.
+        _ => {}
+    }
+}
+
+fn indent(depth: usize) -> String {
+    "  ".repeat(depth)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `soup::Soup::new` runs the full HTML5 parsing algorithm, which wraps
+    // a bare fragment in an implied `<html><head></head><body>...</body>`,
+    // so tests serialize a specific tag rather than the first one in the
+    // document (which would always be `html`).
+    fn sexp(html: &str, tag: &str) -> String {
+        let soup = soup::Soup::new(html);
+        soup
+            .tag(tag)
+            .find()
+            .map(|node| write(&node))
+            .unwrap_or_default()
+    }
+
+    fn sexp_all(html: &str) -> String {
+        let soup = soup::Soup::new(html);
+        soup.children().map(|node| write(&node)).collect()
+    }
+
+    #[test]
+    fn element_with_attrs_and_text() {
+        let out = sexp(r#"<a href="/x">click</a>"#, "a");
+        assert_eq!(out, "(a (@ href \"/x\")\n  \"click\"\n)\n");
+    }
+
+    #[test]
+    fn escapes_quotes_and_backslashes() {
+        let out = sexp(r#"<a title="a &quot;quote&quot; and \ backslash">t</a>"#, "a");
+        assert!(out.contains("\\\"quote\\\""));
+        assert!(out.contains("\\\\ backslash"));
+    }
+
+    #[test]
+    fn escapes_embedded_newlines_in_text() {
+        let out = sexp("<pre>line one\nline two</pre>", "pre");
+        assert!(out.contains("line one\\nline two"));
+        // The escaped text stays on a single raw line.
+        assert_eq!(out.lines().count(), 3);
+    }
+
+    #[test]
+    fn preserves_significant_whitespace_between_inline_elements() {
+        let out = sexp_all("<span>a</span> <span>b</span>");
+        assert!(out.contains("\"a\""));
+        assert!(out.contains("\" \""));
+        assert!(out.contains("\"b\""));
+    }
+}